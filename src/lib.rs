@@ -1,39 +1,271 @@
 #![allow(dead_code)]
 
+use std::collections::HashMap;
 use std::str::FromStr;
 
+/// Maps variable names to their currently bound value, threaded through
+/// evaluation so bindings (see `Expr::Assign`) can survive across calls.
+pub type Env = HashMap<String, Value>;
+
 pub type BoxedExpr = Box<Expr>;
 
+/// A calculator value: either a whole `i64` or, once any floating-point
+/// value enters an expression, an `f64`. This mirrors how dynamically
+/// typed calculators keep numbers as either an integer or a double rather
+/// than committing to one representation up front.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Value {
+    Int(i64),
+    Float(f64),
+}
+
+impl Value {
+    fn as_f64(self) -> f64 {
+        match self {
+            Value::Int(x) => x as f64,
+            Value::Float(x) => x,
+        }
+    }
+}
+
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Int(x) => write!(f, "{x}"),
+            Value::Float(x) if x.is_finite() && x.fract() == 0.0 => write!(f, "{x:.1}"),
+            Value::Float(x) => write!(f, "{x}"),
+        }
+    }
+}
+
+// Applies a binary op, promoting to `Float` if either operand is a float and
+// keeping `Int` only when both operands are. The `Int` path uses a checked
+// operation so overflow surfaces as an `EvalError::Overflow` instead of
+// silently wrapping.
+fn binary_numeric(
+    op_name: &str,
+    a: Value,
+    b: Value,
+    int_op: impl FnOnce(i64, i64) -> Option<i64>,
+    float_op: impl FnOnce(f64, f64) -> f64,
+) -> Result<Value, EvalError> {
+    match (a, b) {
+        (Value::Int(x), Value::Int(y)) => int_op(x, y)
+            .map(Value::Int)
+            .ok_or_else(|| EvalError::Overflow(op_name.to_string(), x, y)),
+        _ => Ok(Value::Float(float_op(a.as_f64(), b.as_f64()))),
+    }
+}
+
+// The primitive operations behind `Expr::eval_with_env` and the bytecode VM
+// (see `run`), factored out so both interpreters agree on arithmetic
+// semantics (promotion, checked overflow, division/modulo by zero).
+fn add_values(a: Value, b: Value) -> Result<Value, EvalError> {
+    binary_numeric("+", a, b, i64::checked_add, |x, y| x + y)
+}
+
+fn sub_values(a: Value, b: Value) -> Result<Value, EvalError> {
+    binary_numeric("-", a, b, i64::checked_sub, |x, y| x - y)
+}
+
+fn mul_values(a: Value, b: Value) -> Result<Value, EvalError> {
+    binary_numeric("*", a, b, i64::checked_mul, |x, y| x * y)
+}
+
+fn sqr_value(a: Value) -> Result<Value, EvalError> {
+    binary_numeric("sqr", a, a, i64::checked_mul, |x, y| x * y)
+}
+
+fn div_values(a: Value, b: Value) -> Result<Value, EvalError> {
+    match (a, b) {
+        (Value::Int(_), Value::Int(0)) => Err(EvalError::DivisionByZero),
+        (Value::Int(x), Value::Int(y)) => Ok(Value::Int(x / y)),
+        _ => Ok(Value::Float(a.as_f64() / b.as_f64())),
+    }
+}
+
+fn mod_values(a: Value, b: Value) -> Result<Value, EvalError> {
+    match (a, b) {
+        (Value::Int(_), Value::Int(0)) => Err(EvalError::DivisionByZero),
+        (Value::Int(x), Value::Int(y)) => x
+            .checked_rem(y)
+            .map(Value::Int)
+            .ok_or_else(|| EvalError::Overflow("%".to_string(), x, y)),
+        _ => Ok(Value::Float(a.as_f64() % b.as_f64())),
+    }
+}
+
+fn pow_values(a: Value, b: Value) -> Result<Value, EvalError> {
+    match (a, b) {
+        (Value::Int(x), Value::Int(y)) => {
+            if y < 0 {
+                return Err(EvalError::NegativeExponent(y));
+            }
+            let exp = u32::try_from(y).map_err(|_| EvalError::Overflow("^".to_string(), x, y))?;
+            x.checked_pow(exp)
+                .map(Value::Int)
+                .ok_or_else(|| EvalError::Overflow("^".to_string(), x, y))
+        }
+        _ => Ok(Value::Float(a.as_f64().powf(b.as_f64()))),
+    }
+}
+
+/// Only `Int` zero/one are treated as identities: an `Int` operand never
+/// promotes the other side to `Float`, but a `Float` operand would, so
+/// dropping a `Float(0.0)`/`Float(1.0)` subtree could silently turn e.g.
+/// `Int(5) + 0.0` (real result `Float(5.0)`) into `Int(5)`.
+fn is_zero(expr: &Expr) -> bool {
+    matches!(expr, Expr::Number(Value::Int(0)))
+}
+
+fn is_one(expr: &Expr) -> bool {
+    matches!(expr, Expr::Number(Value::Int(1)))
+}
+
 #[derive(Debug, PartialEq)]
 pub enum Expr {
-    Number(i64),
+    Number(Value),
+    Var(String),
+    Assign(String, BoxedExpr),
     Add(BoxedExpr, BoxedExpr),
     Sub(BoxedExpr, BoxedExpr),
     Sqr(BoxedExpr),
     Mul(BoxedExpr, BoxedExpr),
     Div(BoxedExpr, BoxedExpr),
+    Mod(BoxedExpr, BoxedExpr),
+    Pow(BoxedExpr, BoxedExpr),
 }
 
 impl Expr {
-    pub fn eval(&self) -> Result<i64, EvalError> {
+    /// Convenience entry point for expressions without variables: evaluates
+    /// against a fresh, empty environment.
+    pub fn eval(&self) -> Result<Value, EvalError> {
+        self.eval_with_env(&mut Env::new())
+    }
+
+    pub fn eval_with_env(&self, env: &mut Env) -> Result<Value, EvalError> {
         Ok(match self {
             Expr::Number(x) => *x,
-            Expr::Add(x, y) => x.eval()? + y.eval()?,
-            Expr::Sub(x, y) => x.eval()? - y.eval()?,
-            Expr::Mul(x, y) => x.eval()? * y.eval()?,
+            Expr::Var(name) => *env
+                .get(name)
+                .ok_or_else(|| EvalError::UndefinedVariable(name.clone()))?,
+            Expr::Assign(name, x) => {
+                let value = x.eval_with_env(env)?;
+                env.insert(name.clone(), value);
+                value
+            }
+            Expr::Add(x, y) => add_values(x.eval_with_env(env)?, y.eval_with_env(env)?)?,
+            Expr::Sub(x, y) => sub_values(x.eval_with_env(env)?, y.eval_with_env(env)?)?,
+            Expr::Mul(x, y) => mul_values(x.eval_with_env(env)?, y.eval_with_env(env)?)?,
+            Expr::Div(x, y) => div_values(x.eval_with_env(env)?, y.eval_with_env(env)?)?,
+            Expr::Sqr(x) => sqr_value(x.eval_with_env(env)?)?,
+            Expr::Mod(x, y) => mod_values(x.eval_with_env(env)?, y.eval_with_env(env)?)?,
+            Expr::Pow(x, y) => pow_values(x.eval_with_env(env)?, y.eval_with_env(env)?)?,
+        })
+    }
+
+    /// Folds constant subtrees into a single `Expr::Number` and applies a
+    /// handful of algebraic identities (`x + 0`, `x * 1`), walking the tree
+    /// bottom-up. `x * 0` becomes `0` only when `x` is itself a constant; a
+    /// non-constant `x` is kept. An error that would occur while folding (e.g.
+    /// division by zero) is left unfolded instead of failing the pass, so
+    /// it still surfaces at eval time.
+    pub fn simplify(self) -> Expr {
+        match self {
+            Expr::Number(_) | Expr::Var(_) => self,
+            Expr::Assign(name, x) => Expr::Assign(name, x.simplify().into()),
+            Expr::Add(x, y) => {
+                let x = x.simplify();
+                let y = y.simplify();
+                if let (Expr::Number(a), Expr::Number(b)) = (&x, &y) {
+                    if let Ok(v) = add_values(*a, *b) {
+                        return Expr::Number(v);
+                    }
+                }
+                if is_zero(&y) {
+                    return x;
+                }
+                if is_zero(&x) {
+                    return y;
+                }
+                Expr::Add(x.into(), y.into())
+            }
+            Expr::Sub(x, y) => {
+                let x = x.simplify();
+                let y = y.simplify();
+                if let (Expr::Number(a), Expr::Number(b)) = (&x, &y) {
+                    if let Ok(v) = sub_values(*a, *b) {
+                        return Expr::Number(v);
+                    }
+                }
+                Expr::Sub(x.into(), y.into())
+            }
+            Expr::Mul(x, y) => {
+                let x = x.simplify();
+                let y = y.simplify();
+                if let (Expr::Number(a), Expr::Number(b)) = (&x, &y) {
+                    if let Ok(v) = mul_values(*a, *b) {
+                        return Expr::Number(v);
+                    }
+                }
+                // Only fold `x * 0` away when the other side is itself a
+                // constant: otherwise `x` could be an error-producing or
+                // type-promoting subtree (e.g. `undef * 0` or `1 0 / 0 *`)
+                // and dropping it would swallow that at eval time.
+                if is_zero(&x) && matches!(y, Expr::Number(_)) {
+                    return Expr::Number(Value::Int(0));
+                }
+                if is_zero(&y) && matches!(x, Expr::Number(_)) {
+                    return Expr::Number(Value::Int(0));
+                }
+                if is_one(&y) {
+                    return x;
+                }
+                if is_one(&x) {
+                    return y;
+                }
+                Expr::Mul(x.into(), y.into())
+            }
             Expr::Div(x, y) => {
-                let y = y.eval()?;
-                if y == 0 {
-                    return Err(EvalError::DivisionByZero);
-                } else {
-                    x.eval()? / y
+                let x = x.simplify();
+                let y = y.simplify();
+                if let (Expr::Number(a), Expr::Number(b)) = (&x, &y) {
+                    if let Ok(v) = div_values(*a, *b) {
+                        return Expr::Number(v);
+                    }
+                }
+                Expr::Div(x.into(), y.into())
+            }
+            Expr::Mod(x, y) => {
+                let x = x.simplify();
+                let y = y.simplify();
+                if let (Expr::Number(a), Expr::Number(b)) = (&x, &y) {
+                    if let Ok(v) = mod_values(*a, *b) {
+                        return Expr::Number(v);
+                    }
+                }
+                Expr::Mod(x.into(), y.into())
+            }
+            Expr::Pow(x, y) => {
+                let x = x.simplify();
+                let y = y.simplify();
+                if let (Expr::Number(a), Expr::Number(b)) = (&x, &y) {
+                    if let Ok(v) = pow_values(*a, *b) {
+                        return Expr::Number(v);
+                    }
                 }
+                Expr::Pow(x.into(), y.into())
             }
             Expr::Sqr(x) => {
-                let x = x.eval()?;
-                x * x
+                let x = x.simplify();
+                if let Expr::Number(a) = &x {
+                    if let Ok(v) = sqr_value(*a) {
+                        return Expr::Number(v);
+                    }
+                }
+                Expr::Sqr(x.into())
             }
-        })
+        }
     }
 }
 
@@ -48,12 +280,25 @@ impl FromStr for Expr {
 #[derive(Debug, PartialEq)]
 pub enum EvalError {
     DivisionByZero,
+    UndefinedVariable(String),
+    /// Carries the operator name and the two operands that overflowed.
+    Overflow(String, i64, i64),
+    /// An integer base was raised to this negative exponent, which can't be
+    /// represented as an `i64` result.
+    NegativeExponent(i64),
 }
 
 impl std::fmt::Display for EvalError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             EvalError::DivisionByZero => write!(f, "Divistion by zero"),
+            EvalError::UndefinedVariable(name) => write!(f, "Undefined variable: {name}"),
+            EvalError::Overflow(op, lhs, rhs) => {
+                write!(f, "Integer overflow: {lhs} {op} {rhs}")
+            }
+            EvalError::NegativeExponent(exp) => {
+                write!(f, "Negative exponent not supported for integers: {exp}")
+            }
         }
     }
 }
@@ -70,6 +315,8 @@ pub enum ParseError {
     EmptyInput,
     #[error("Left arguments")]
     LeftArguments,
+    #[error("Unbalanced parentheses")]
+    UnbalancedParens,
 }
 
 #[derive(Debug, PartialEq, thiserror::Error)]
@@ -80,8 +327,152 @@ pub enum ParseOrEvalError {
     Eval(#[from] EvalError),
 }
 
-pub fn eval_str(s: &str) -> Result<i64, ParseOrEvalError> {
-    Ok(s.parse::<Expr>()?.eval()?) // automatic conversion between error types due to implementation of From<>
+pub fn eval_str(s: &str) -> Result<Value, ParseOrEvalError> {
+    // Try the postfix (RPN) dialect first, and fall back to infix/algebraic
+    // notation so callers can use either without choosing up front.
+    let expr = match parse(s) {
+        Ok(expr) => expr,
+        Err(_) => parse_infix(s)?,
+    };
+    Ok(expr.eval()?)
+}
+
+/// Like `eval_str`, but evaluates against the given environment so variable
+/// bindings made with `=` persist across calls.
+pub fn eval_str_with_env(s: &str, env: &mut Env) -> Result<Value, ParseOrEvalError> {
+    let expr = match parse(s) {
+        Ok(expr) => expr,
+        Err(_) => parse_infix(s)?,
+    };
+    Ok(expr.eval_with_env(env)?)
+}
+
+/// A single instruction for the stack machine in `run`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Op {
+    Push(Value),
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Sqr,
+    Mod,
+    Pow,
+}
+
+#[derive(Debug, PartialEq, thiserror::Error)]
+pub enum CompileError {
+    #[error("Cannot compile expression referencing variables: {0}")]
+    UnsupportedExpr(String),
+}
+
+/// Lowers an `Expr` tree into a flat `Op` sequence for the stack machine
+/// `run` interprets, via a post-order traversal so operands are emitted
+/// before the operator that consumes them (the same order the RPN parser
+/// already consumes).
+pub fn compile(expr: &Expr) -> Result<Vec<Op>, CompileError> {
+    let mut ops = Vec::new();
+    compile_into(expr, &mut ops)?;
+    Ok(ops)
+}
+
+fn compile_into(expr: &Expr, ops: &mut Vec<Op>) -> Result<(), CompileError> {
+    match expr {
+        Expr::Number(x) => ops.push(Op::Push(*x)),
+        Expr::Var(_) | Expr::Assign(_, _) => {
+            return Err(CompileError::UnsupportedExpr(format!("{expr:?}")))
+        }
+        Expr::Add(x, y) => {
+            compile_into(x, ops)?;
+            compile_into(y, ops)?;
+            ops.push(Op::Add);
+        }
+        Expr::Sub(x, y) => {
+            compile_into(x, ops)?;
+            compile_into(y, ops)?;
+            ops.push(Op::Sub);
+        }
+        Expr::Mul(x, y) => {
+            compile_into(x, ops)?;
+            compile_into(y, ops)?;
+            ops.push(Op::Mul);
+        }
+        Expr::Div(x, y) => {
+            compile_into(x, ops)?;
+            compile_into(y, ops)?;
+            ops.push(Op::Div);
+        }
+        Expr::Mod(x, y) => {
+            compile_into(x, ops)?;
+            compile_into(y, ops)?;
+            ops.push(Op::Mod);
+        }
+        Expr::Pow(x, y) => {
+            compile_into(x, ops)?;
+            compile_into(y, ops)?;
+            ops.push(Op::Pow);
+        }
+        Expr::Sqr(x) => {
+            compile_into(x, ops)?;
+            ops.push(Op::Sqr);
+        }
+    }
+    Ok(())
+}
+
+/// Runs a compiled program on a stack machine: `Push` puts a value on the
+/// operand stack, and each op pops its operands, applies itself, and pushes
+/// the result back. After the last instruction the single remaining entry
+/// is the answer.
+pub fn run(ops: &[Op]) -> Result<Value, EvalError> {
+    let mut stack: Vec<Value> = Vec::new();
+
+    for op in ops {
+        match op {
+            Op::Push(x) => stack.push(*x),
+            Op::Sqr => {
+                let x = stack.pop().expect("malformed bytecode: empty stack");
+                stack.push(sqr_value(x)?);
+            }
+            _ => {
+                let y = stack.pop().expect("malformed bytecode: empty stack");
+                let x = stack.pop().expect("malformed bytecode: empty stack");
+                let result = match op {
+                    Op::Add => add_values(x, y)?,
+                    Op::Sub => sub_values(x, y)?,
+                    Op::Mul => mul_values(x, y)?,
+                    Op::Div => div_values(x, y)?,
+                    Op::Mod => mod_values(x, y)?,
+                    Op::Pow => pow_values(x, y)?,
+                    Op::Push(_) | Op::Sqr => unreachable!(),
+                };
+                stack.push(result);
+            }
+        }
+    }
+
+    Ok(stack.pop().expect("malformed bytecode: empty program"))
+}
+
+#[derive(Debug, PartialEq, thiserror::Error)]
+pub enum CompiledEvalError {
+    #[error("Parse error: {0}")]
+    Parse(#[from] ParseError),
+    #[error("Compile error: {0}")]
+    Compile(#[from] CompileError),
+    #[error("Evaluation error: {0}")]
+    Eval(#[from] EvalError),
+}
+
+/// Alternative to `eval_str` that compiles to bytecode and runs it on the
+/// VM instead of walking the `Expr` tree directly.
+pub fn eval_str_compiled(s: &str) -> Result<Value, CompiledEvalError> {
+    let expr = match parse(s) {
+        Ok(expr) => expr,
+        Err(_) => parse_infix(s)?,
+    };
+    let ops = compile(&expr)?;
+    Ok(run(&ops)?)
 }
 
 /*fn eval(expr: &Expr) -> Result<i64, EvalError> {
@@ -105,7 +496,15 @@ pub fn eval_str(s: &str) -> Result<i64, ParseOrEvalError> {
     })
 }*/
 
-// compatible input: "3 sqr 4 sqr + 5 sqr -"
+fn is_identifier(word: &str) -> bool {
+    let mut chars = word.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+// compatible input: "3 sqr 4 sqr + 5 sqr -", "x 5 =", "x x * 3 +"
 fn parse(input: &str) -> Result<Expr, ParseError> {
     let mut stack: Vec<Expr> = Vec::new();
 
@@ -131,15 +530,41 @@ fn parse(input: &str) -> Result<Expr, ParseError> {
                 let y = stack.pop().ok_or(ParseError::WrongArgumentsCount)?;
                 stack.push(Expr::Div(y.into(), x.into()))
             }
+            "%" => {
+                let x = stack.pop().ok_or(ParseError::WrongArgumentsCount)?;
+                let y = stack.pop().ok_or(ParseError::WrongArgumentsCount)?;
+                stack.push(Expr::Mod(y.into(), x.into()))
+            }
+            "^" | "pow" => {
+                let x = stack.pop().ok_or(ParseError::WrongArgumentsCount)?;
+                let y = stack.pop().ok_or(ParseError::WrongArgumentsCount)?;
+                stack.push(Expr::Pow(y.into(), x.into()))
+            }
             "sqr" => {
                 let x = stack.pop().ok_or(ParseError::WrongArgumentsCount)?;
                 stack.push(Expr::Sqr(x.into()))
             }
+            "=" => {
+                let value = stack.pop().ok_or(ParseError::WrongArgumentsCount)?;
+                let target = stack.pop().ok_or(ParseError::WrongArgumentsCount)?;
+                let name = match target {
+                    Expr::Var(name) => name,
+                    _ => return Err(ParseError::InvalidInput("=".to_string())),
+                };
+                stack.push(Expr::Assign(name, value.into()))
+            }
             _ => {
-                let x = word
-                    .parse::<i64>()
-                    .map_err(|_| ParseError::InvalidInput(word.to_string()))?;
-                stack.push(Expr::Number(x));
+                let value = if let Ok(x) = word.parse::<i64>() {
+                    Value::Int(x)
+                } else if let Ok(x) = word.parse::<f64>() {
+                    Value::Float(x)
+                } else if is_identifier(word) {
+                    stack.push(Expr::Var(word.to_string()));
+                    continue;
+                } else {
+                    return Err(ParseError::InvalidInput(word.to_string()));
+                };
+                stack.push(Expr::Number(value));
             }
         }
     }
@@ -151,6 +576,187 @@ fn parse(input: &str) -> Result<Expr, ParseError> {
     }
 }
 
+#[derive(Clone, Copy, PartialEq)]
+enum InfixOp {
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Sqr,
+    LParen,
+}
+
+impl InfixOp {
+    fn precedence(self) -> u8 {
+        match self {
+            InfixOp::Plus | InfixOp::Minus => 1,
+            InfixOp::Star | InfixOp::Slash => 2,
+            InfixOp::Sqr => 3,
+            InfixOp::LParen => 0,
+        }
+    }
+}
+
+fn apply_infix_op(op: InfixOp, operands: &mut Vec<Expr>) -> Result<(), ParseError> {
+    if op == InfixOp::Sqr {
+        let x = operands.pop().ok_or(ParseError::WrongArgumentsCount)?;
+        operands.push(Expr::Sqr(x.into()));
+        return Ok(());
+    }
+
+    let x = operands.pop().ok_or(ParseError::WrongArgumentsCount)?;
+    let y = operands.pop().ok_or(ParseError::WrongArgumentsCount)?;
+    operands.push(match op {
+        InfixOp::Plus => Expr::Add(y.into(), x.into()),
+        InfixOp::Minus => Expr::Sub(y.into(), x.into()),
+        InfixOp::Star => Expr::Mul(y.into(), x.into()),
+        InfixOp::Slash => Expr::Div(y.into(), x.into()),
+        InfixOp::Sqr | InfixOp::LParen => unreachable!(),
+    });
+    Ok(())
+}
+
+// compatible input: "(3 + 4) * 5 - 6 / 2", "25 sqr", "sqr(25)"
+//
+// Shunting-yard: numbers are pushed straight onto the operand stack; each
+// operator pops operators of greater-or-equal precedence off the operator
+// stack (applying them to the operand stack) before being pushed itself.
+// `sqr` is unary: used as a prefix it behaves like a function (optionally
+// followed by parens), used as a postfix it applies immediately to the
+// preceding operand.
+fn parse_infix(input: &str) -> Result<Expr, ParseError> {
+    let mut operands: Vec<Expr> = Vec::new();
+    let mut operators: Vec<InfixOp> = Vec::new();
+    let mut expect_operand = true;
+
+    let chars: Vec<char> = input.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        if c.is_ascii_digit() {
+            let start = i;
+            while i < chars.len() && chars[i].is_ascii_digit() {
+                i += 1;
+            }
+            if i < chars.len()
+                && chars[i] == '.'
+                && chars.get(i + 1).is_some_and(char::is_ascii_digit)
+            {
+                i += 1;
+                while i < chars.len() && chars[i].is_ascii_digit() {
+                    i += 1;
+                }
+            }
+            if i < chars.len() && (chars[i] == 'e' || chars[i] == 'E') {
+                let mut end = i + 1;
+                if chars.get(end).is_some_and(|c| *c == '+' || *c == '-') {
+                    end += 1;
+                }
+                if chars.get(end).is_some_and(char::is_ascii_digit) {
+                    while chars.get(end).is_some_and(char::is_ascii_digit) {
+                        end += 1;
+                    }
+                    i = end;
+                }
+            }
+            let word: String = chars[start..i].iter().collect();
+            let value = if word.contains(['.', 'e', 'E']) {
+                Value::Float(
+                    word.parse::<f64>()
+                        .map_err(|_| ParseError::InvalidInput(word.clone()))?,
+                )
+            } else {
+                Value::Int(
+                    word.parse::<i64>()
+                        .map_err(|_| ParseError::InvalidInput(word.clone()))?,
+                )
+            };
+            operands.push(Expr::Number(value));
+            expect_operand = false;
+            continue;
+        }
+
+        if c.is_ascii_alphabetic() {
+            let start = i;
+            while i < chars.len() && chars[i].is_ascii_alphabetic() {
+                i += 1;
+            }
+            let word: String = chars[start..i].iter().collect();
+            if word != "sqr" {
+                return Err(ParseError::InvalidInput(word));
+            }
+            if expect_operand {
+                // Prefix/function form, e.g. `sqr(25)` or `sqr 25`.
+                operators.push(InfixOp::Sqr);
+            } else {
+                // Postfix form, e.g. `25 sqr`: apply right away.
+                apply_infix_op(InfixOp::Sqr, &mut operands)?;
+            }
+            continue;
+        }
+
+        match c {
+            '(' => {
+                operators.push(InfixOp::LParen);
+                expect_operand = true;
+            }
+            ')' => {
+                loop {
+                    match operators.pop() {
+                        Some(InfixOp::LParen) => break,
+                        Some(op) => apply_infix_op(op, &mut operands)?,
+                        None => return Err(ParseError::UnbalancedParens),
+                    }
+                }
+                if operators.last() == Some(&InfixOp::Sqr) {
+                    operators.pop();
+                    apply_infix_op(InfixOp::Sqr, &mut operands)?;
+                }
+                expect_operand = false;
+            }
+            '+' | '-' | '*' | '/' => {
+                let op = match c {
+                    '+' => InfixOp::Plus,
+                    '-' => InfixOp::Minus,
+                    '*' => InfixOp::Star,
+                    '/' => InfixOp::Slash,
+                    _ => unreachable!(),
+                };
+                while let Some(&top) = operators.last() {
+                    if top != InfixOp::LParen && top.precedence() >= op.precedence() {
+                        operators.pop();
+                        apply_infix_op(top, &mut operands)?;
+                    } else {
+                        break;
+                    }
+                }
+                operators.push(op);
+                expect_operand = true;
+            }
+            _ => return Err(ParseError::InvalidInput(c.to_string())),
+        }
+        i += 1;
+    }
+
+    while let Some(op) = operators.pop() {
+        if op == InfixOp::LParen {
+            return Err(ParseError::UnbalancedParens);
+        }
+        apply_infix_op(op, &mut operands)?;
+    }
+
+    if operands.len() > 1 {
+        Err(ParseError::LeftArguments)
+    } else {
+        operands.pop().ok_or(ParseError::EmptyInput)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -160,7 +766,7 @@ mod tests {
         let input = "1 1 +";
         let res_p = parse(input).unwrap();
         let res = res_p.eval().unwrap();
-        assert_eq!(res, 2);
+        assert_eq!(res, Value::Int(2));
     }
 
     #[test]
@@ -168,7 +774,7 @@ mod tests {
         let input = "2 1 -";
         let res_p = parse(input).unwrap();
         let res = res_p.eval().unwrap();
-        assert_eq!(res, 1);
+        assert_eq!(res, Value::Int(1));
     }
 
     #[test]
@@ -176,7 +782,7 @@ mod tests {
         let input = "2 3 *";
         let res_p = parse(input).unwrap();
         let res = res_p.eval().unwrap();
-        assert_eq!(res, 6);
+        assert_eq!(res, Value::Int(6));
     }
 
     #[test]
@@ -184,7 +790,7 @@ mod tests {
         let input = "4 2 /";
         let res_p = parse(input).unwrap();
         let res = res_p.eval().unwrap();
-        assert_eq!(res, 2);
+        assert_eq!(res, Value::Int(2));
     }
 
     #[test]
@@ -192,7 +798,7 @@ mod tests {
         let input = "4 sqr";
         let res_p = parse(input).unwrap();
         let res = res_p.eval().unwrap();
-        assert_eq!(res, 16);
+        assert_eq!(res, Value::Int(16));
     }
 
     #[test]
@@ -200,7 +806,7 @@ mod tests {
         let input = "4 2 + 6 *";
         let res_p = parse(input).unwrap();
         let res = res_p.eval().unwrap();
-        assert_eq!(res, 36);
+        assert_eq!(res, Value::Int(36));
     }
 
     #[test]
@@ -208,7 +814,7 @@ mod tests {
         let input = "4 2 + 1 * sqr";
         let res_p = parse(input).unwrap();
         let res = res_p.eval().unwrap();
-        assert_eq!(res, 36);
+        assert_eq!(res, Value::Int(36));
     }
 
     #[test]
@@ -216,7 +822,7 @@ mod tests {
         let input = "3 sqr 4 sqr + 5 sqr -";
         let res_p = parse(input).unwrap();
         let res = res_p.eval().unwrap();
-        assert_eq!(res, 0);
+        assert_eq!(res, Value::Int(0));
     }
 
     #[test]
@@ -224,7 +830,7 @@ mod tests {
         let input = "1";
         let res_p = parse(input).unwrap();
         let res = res_p.eval().unwrap();
-        assert_eq!(res, 1);
+        assert_eq!(res, Value::Int(1));
     }
 
     #[test]
@@ -243,12 +849,9 @@ mod tests {
 
     #[test]
     fn parse_error_3() {
-        let input = "something";
+        let input = "1.2.3";
         let res = parse(input);
-        assert_eq!(
-            res,
-            Err(ParseError::InvalidInput(String::from("something")))
-        );
+        assert_eq!(res, Err(ParseError::InvalidInput(String::from("1.2.3"))));
     }
 
     #[test]
@@ -260,49 +863,64 @@ mod tests {
 
     #[test]
     fn test_add() {
-        let expr = Expr::Add(Expr::Number(1).into(), Expr::Number(2).into());
+        let expr = Expr::Add(
+            Expr::Number(Value::Int(1)).into(),
+            Expr::Number(Value::Int(2)).into(),
+        );
         let res = expr.eval().unwrap();
-        assert_eq!(res, 3)
+        assert_eq!(res, Value::Int(3))
     }
 
     #[test]
     fn test_sub() {
-        let expr = Expr::Sub(Expr::Number(1).into(), Expr::Number(2).into());
+        let expr = Expr::Sub(
+            Expr::Number(Value::Int(1)).into(),
+            Expr::Number(Value::Int(2)).into(),
+        );
         let res = expr.eval().unwrap();
-        assert_eq!(res, -1)
+        assert_eq!(res, Value::Int(-1))
     }
 
     #[test]
     fn test_mul() {
-        let expr = Expr::Mul(Expr::Number(3).into(), Expr::Number(2).into());
+        let expr = Expr::Mul(
+            Expr::Number(Value::Int(3)).into(),
+            Expr::Number(Value::Int(2)).into(),
+        );
         let res = expr.eval().unwrap();
-        assert_eq!(res, 6)
+        assert_eq!(res, Value::Int(6))
     }
 
     #[test]
     fn test_div() {
-        let expr = Expr::Div(Expr::Number(3).into(), Expr::Number(2).into());
+        let expr = Expr::Div(
+            Expr::Number(Value::Int(3)).into(),
+            Expr::Number(Value::Int(2)).into(),
+        );
         let res = expr.eval().unwrap();
-        assert_eq!(res, 1)
+        assert_eq!(res, Value::Int(1))
     }
 
     #[test]
     fn test_number() {
-        let expr = Expr::Number(123);
+        let expr = Expr::Number(Value::Int(123));
         let res = expr.eval().unwrap();
-        assert_eq!(res, 123)
+        assert_eq!(res, Value::Int(123))
     }
 
     #[test]
     fn test_sqrt() {
-        let expr = Expr::Sqr(Expr::Number(4).into());
+        let expr = Expr::Sqr(Expr::Number(Value::Int(4)).into());
         let res = expr.eval().unwrap();
-        assert_eq!(res, 16)
+        assert_eq!(res, Value::Int(16))
     }
 
     #[test]
     fn test_div_zero() {
-        let expr = Expr::Div(Expr::Number(-1).into(), Expr::Number(0).into());
+        let expr = Expr::Div(
+            Expr::Number(Value::Int(-1)).into(),
+            Expr::Number(Value::Int(0)).into(),
+        );
         let res = expr.eval();
         assert_eq!(res, Err(EvalError::DivisionByZero))
     }
@@ -310,17 +928,379 @@ mod tests {
     #[test]
     fn test_complicated() {
         let expr = Expr::Add(
-            Expr::Mul(Expr::Number(-1).into(), Expr::Number(2).into()).into(),
-            Expr::Sqr(Expr::Number(25).into()).into(),
+            Expr::Mul(
+                Expr::Number(Value::Int(-1)).into(),
+                Expr::Number(Value::Int(2)).into(),
+            )
+            .into(),
+            Expr::Sqr(Expr::Number(Value::Int(25)).into()).into(),
         );
         let res = expr.eval().unwrap();
-        assert_eq!(res, 623)
+        assert_eq!(res, Value::Int(623))
     }
 
     #[test]
     fn test_from_str() {
         let expr = Expr::from_str("4 2 + 3 *").unwrap();
         let res = expr.eval().unwrap();
-        assert_eq!(res, 18)
+        assert_eq!(res, Value::Int(18))
+    }
+
+    #[test]
+    fn parse_infix_simple() {
+        let res_p = parse_infix("1 + 1").unwrap();
+        let res = res_p.eval().unwrap();
+        assert_eq!(res, Value::Int(2));
+    }
+
+    #[test]
+    fn parse_infix_precedence() {
+        let res_p = parse_infix("2 + 3 * 4").unwrap();
+        let res = res_p.eval().unwrap();
+        assert_eq!(res, Value::Int(14));
+    }
+
+    #[test]
+    fn parse_infix_parens() {
+        let res_p = parse_infix("(3 + 4) * 5 - 6 / 2").unwrap();
+        let res = res_p.eval().unwrap();
+        assert_eq!(res, Value::Int(32));
+    }
+
+    #[test]
+    fn parse_infix_postfix_sqr() {
+        let res_p = parse_infix("25 sqr").unwrap();
+        let res = res_p.eval().unwrap();
+        assert_eq!(res, Value::Int(625));
+    }
+
+    #[test]
+    fn parse_infix_function_sqr() {
+        let res_p = parse_infix("sqr(25)").unwrap();
+        let res = res_p.eval().unwrap();
+        assert_eq!(res, Value::Int(625));
+    }
+
+    #[test]
+    fn parse_infix_unbalanced_parens() {
+        let res = parse_infix("(3 + 4");
+        assert_eq!(res, Err(ParseError::UnbalancedParens));
+    }
+
+    #[test]
+    fn parse_infix_extra_closing_paren() {
+        let res = parse_infix("3 + 4)");
+        assert_eq!(res, Err(ParseError::UnbalancedParens));
+    }
+
+    #[test]
+    fn eval_str_accepts_infix() {
+        let res = eval_str("(3 + 4) * 5 - 6 / 2").unwrap();
+        assert_eq!(res, Value::Int(32));
+    }
+
+    #[test]
+    fn parse_float_literal() {
+        let res = eval_str("2.5").unwrap();
+        assert_eq!(res, Value::Float(2.5));
+    }
+
+    #[test]
+    fn div_promotes_to_float() {
+        let res = eval_str("1 2.0 /").unwrap();
+        assert_eq!(res, Value::Float(0.5));
+    }
+
+    #[test]
+    fn div_stays_int_when_both_operands_int() {
+        let res = eval_str("7 2 /").unwrap();
+        assert_eq!(res, Value::Int(3));
+    }
+
+    #[test]
+    fn mixed_int_float_add_promotes() {
+        let res = eval_str("1 2.5 +").unwrap();
+        assert_eq!(res, Value::Float(3.5));
+    }
+
+    #[test]
+    fn float_sqr() {
+        let res = eval_str("2.5 sqr").unwrap();
+        assert_eq!(res, Value::Float(6.25));
+    }
+
+    #[test]
+    fn display_int_has_no_trailing_zero() {
+        assert_eq!(Value::Int(5).to_string(), "5");
+    }
+
+    #[test]
+    fn display_whole_float_keeps_trailing_zero() {
+        assert_eq!(Value::Float(5.0).to_string(), "5.0");
+    }
+
+    #[test]
+    fn display_fractional_float() {
+        assert_eq!(Value::Float(1.25).to_string(), "1.25");
+    }
+
+    #[test]
+    fn parse_assign_and_var() {
+        let expr = parse("x 5 =").unwrap();
+        assert_eq!(
+            expr,
+            Expr::Assign("x".to_string(), Expr::Number(Value::Int(5)).into())
+        );
+    }
+
+    #[test]
+    fn assign_binds_and_returns_value() {
+        let mut env = Env::new();
+        let expr = parse("x 5 =").unwrap();
+        let res = expr.eval_with_env(&mut env).unwrap();
+        assert_eq!(res, Value::Int(5));
+        assert_eq!(env.get("x"), Some(&Value::Int(5)));
+    }
+
+    #[test]
+    fn reuse_variable_across_entries() {
+        let mut env = Env::new();
+        eval_str_with_env("x 5 =", &mut env).unwrap();
+        let res = eval_str_with_env("x x * 3 +", &mut env).unwrap();
+        assert_eq!(res, Value::Int(28));
+    }
+
+    #[test]
+    fn undefined_variable_errors() {
+        let expr = parse("x").unwrap();
+        let res = expr.eval();
+        assert_eq!(res, Err(EvalError::UndefinedVariable("x".to_string())));
+    }
+
+    #[test]
+    fn add_overflow_errors() {
+        let expr = Expr::Add(
+            Expr::Number(Value::Int(i64::MAX)).into(),
+            Expr::Number(Value::Int(1)).into(),
+        );
+        let res = expr.eval();
+        assert_eq!(res, Err(EvalError::Overflow("+".to_string(), i64::MAX, 1)));
+    }
+
+    #[test]
+    fn sqr_overflow_errors() {
+        let expr = Expr::Sqr(Expr::Number(Value::Int(4_000_000_000)).into());
+        let res = expr.eval();
+        assert_eq!(
+            res,
+            Err(EvalError::Overflow(
+                "sqr".to_string(),
+                4_000_000_000,
+                4_000_000_000
+            ))
+        );
+    }
+
+    #[test]
+    fn parse_mod() {
+        let res = parse("7 3 %").unwrap().eval().unwrap();
+        assert_eq!(res, Value::Int(1));
+    }
+
+    #[test]
+    fn mod_by_zero_errors() {
+        let res = parse("7 0 %").unwrap().eval();
+        assert_eq!(res, Err(EvalError::DivisionByZero));
+    }
+
+    #[test]
+    fn parse_pow() {
+        let res = parse("2 10 ^").unwrap().eval().unwrap();
+        assert_eq!(res, Value::Int(1024));
+    }
+
+    #[test]
+    fn parse_pow_keyword() {
+        let res = parse("2 10 pow").unwrap().eval().unwrap();
+        assert_eq!(res, Value::Int(1024));
+    }
+
+    #[test]
+    fn sqr_equivalent_to_pow_2() {
+        let sqr = parse("5 sqr").unwrap().eval().unwrap();
+        let pow = parse("5 2 ^").unwrap().eval().unwrap();
+        assert_eq!(sqr, pow);
+    }
+
+    #[test]
+    fn pow_negative_exponent_errors() {
+        let expr = Expr::Pow(
+            Expr::Number(Value::Int(2)).into(),
+            Expr::Number(Value::Int(-1)).into(),
+        );
+        assert_eq!(expr.eval(), Err(EvalError::NegativeExponent(-1)));
+    }
+
+    #[test]
+    fn pow_overflow_errors() {
+        let expr = Expr::Pow(
+            Expr::Number(Value::Int(2)).into(),
+            Expr::Number(Value::Int(100)).into(),
+        );
+        assert_eq!(
+            expr.eval(),
+            Err(EvalError::Overflow("^".to_string(), 2, 100))
+        );
+    }
+
+    #[test]
+    fn float_mod_and_pow() {
+        let res = eval_str("5.5 2 %").unwrap();
+        assert_eq!(res, Value::Float(1.5));
+        let res = eval_str("2.0 3 ^").unwrap();
+        assert_eq!(res, Value::Float(8.0));
+    }
+
+    #[test]
+    fn compile_matches_eval() {
+        for input in [
+            "1 1 +",
+            "4 2 + 6 *",
+            "3 sqr 4 sqr + 5 sqr -",
+            "7 3 %",
+            "2 10 ^",
+            "1 2 /",
+        ] {
+            let expr = parse(input).unwrap();
+            let direct = expr.eval().unwrap();
+            let compiled = eval_str_compiled(input).unwrap();
+            assert_eq!(direct, compiled, "mismatch for {input}");
+        }
+    }
+
+    #[test]
+    fn compile_is_post_order() {
+        let expr = parse("4 2 +").unwrap();
+        let ops = compile(&expr).unwrap();
+        assert_eq!(
+            ops,
+            vec![Op::Push(Value::Int(4)), Op::Push(Value::Int(2)), Op::Add]
+        );
+    }
+
+    #[test]
+    fn run_reports_division_by_zero() {
+        let ops = vec![Op::Push(Value::Int(1)), Op::Push(Value::Int(0)), Op::Div];
+        assert_eq!(run(&ops), Err(EvalError::DivisionByZero));
+    }
+
+    #[test]
+    fn compile_rejects_variables() {
+        let expr = parse("x 5 =").unwrap();
+        assert!(matches!(
+            compile(&expr),
+            Err(CompileError::UnsupportedExpr(_))
+        ));
+    }
+
+    #[test]
+    fn simplify_folds_constants() {
+        let expr = parse("2 3 +").unwrap().simplify();
+        assert_eq!(expr, Expr::Number(Value::Int(5)));
+    }
+
+    #[test]
+    fn simplify_folds_nested_constants() {
+        let expr = parse("2 3 + 4 *").unwrap().simplify();
+        assert_eq!(expr, Expr::Number(Value::Int(20)));
+    }
+
+    #[test]
+    fn simplify_add_zero_identity() {
+        let expr = parse("x 0 +").unwrap().simplify();
+        assert_eq!(expr, Expr::Var("x".to_string()));
+    }
+
+    #[test]
+    fn simplify_mul_one_identity() {
+        let expr = parse("x 1 *").unwrap().simplify();
+        assert_eq!(expr, Expr::Var("x".to_string()));
+    }
+
+    #[test]
+    fn simplify_mul_zero_identity() {
+        let expr = parse("5 0 *").unwrap().simplify();
+        assert_eq!(expr, Expr::Number(Value::Int(0)));
+    }
+
+    #[test]
+    fn simplify_mul_zero_preserves_error_subtree() {
+        // `x` is undefined, so `x * 0` must not fold away the error.
+        let expr = parse("x 0 *").unwrap().simplify();
+        assert_eq!(
+            expr,
+            Expr::Mul(
+                Expr::Var("x".to_string()).into(),
+                Expr::Number(Value::Int(0)).into()
+            )
+        );
+        assert_eq!(
+            expr.eval(),
+            Err(EvalError::UndefinedVariable("x".to_string()))
+        );
+    }
+
+    #[test]
+    fn simplify_mul_zero_preserves_division_by_zero() {
+        // Folding `x * 0` to `0` here would swallow the division-by-zero
+        // that should still surface at eval time.
+        let expr = parse("1 0 / 0 *").unwrap().simplify();
+        assert_eq!(expr.eval(), Err(EvalError::DivisionByZero));
+    }
+
+    #[test]
+    fn simplify_add_zero_float_preserves_promotion() {
+        let mut env = Env::new();
+        env.insert("x".to_string(), Value::Int(5));
+        let expr = parse("x 0.0 +").unwrap();
+        let before = expr.eval_with_env(&mut env.clone()).unwrap();
+        let after = expr.simplify().eval_with_env(&mut env).unwrap();
+        assert_eq!(before, Value::Float(5.0));
+        assert_eq!(after, Value::Float(5.0));
+    }
+
+    #[test]
+    fn simplify_mul_one_float_preserves_promotion() {
+        let mut env = Env::new();
+        env.insert("x".to_string(), Value::Int(5));
+        let expr = parse("x 1.0 *").unwrap();
+        let before = expr.eval_with_env(&mut env.clone()).unwrap();
+        let after = expr.simplify().eval_with_env(&mut env).unwrap();
+        assert_eq!(before, Value::Float(5.0));
+        assert_eq!(after, Value::Float(5.0));
+    }
+
+    #[test]
+    fn simplify_leaves_division_by_zero_unfolded() {
+        let expr = parse("1 0 /").unwrap().simplify();
+        assert_eq!(
+            expr,
+            Expr::Div(
+                Expr::Number(Value::Int(1)).into(),
+                Expr::Number(Value::Int(0)).into()
+            )
+        );
+        assert_eq!(expr.eval(), Err(EvalError::DivisionByZero));
+    }
+
+    #[test]
+    fn simplify_preserves_value_for_non_constant() {
+        let mut env = Env::new();
+        env.insert("x".to_string(), Value::Int(7));
+        let expr = parse("x 2 + 3 *").unwrap();
+        let before = expr.eval_with_env(&mut env.clone()).unwrap();
+        let simplified = expr.simplify();
+        let after = simplified.eval_with_env(&mut env).unwrap();
+        assert_eq!(before, after);
     }
 }
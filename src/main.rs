@@ -1,14 +1,15 @@
-use calc::eval_str;
+use calc::{eval_str_with_env, Env};
 use std::io;
 
 fn main() -> Result<(), io::Error> {
+    let mut env = Env::new();
     loop {
         println!("Enter equation (or Enter to finish):");
         let mut buf = String::new();
         if io::stdin().read_line(&mut buf)? == 1 {
             break;
         } else {
-            match eval_str(&buf) {
+            match eval_str_with_env(&buf, &mut env) {
                 Ok(result) => println!("Result: {result}"),
                 Err(e) => println!("Error occured: {e}"),
             }